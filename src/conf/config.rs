@@ -0,0 +1,156 @@
+// src/conf/config.rs
+// Layered config parsing, modeled on Mercurial's config layers: each source
+// file is its own layer, `%include` splices another fragment's layer in at
+// that point (with cycle detection), and `%unset` removes a previously-set
+// key from anything layered below it. This lets a module ship its own
+// `meta-hybrid.conf` declaring `mode=magic`/`mode=auto` without needing a
+// single central file to know about every module.
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::defs;
+use crate::mount::overlay::Propagation;
+
+#[derive(Debug, Default, Clone)]
+pub struct Config {
+    pub partitions: Vec<String>,
+    pub moduledir: PathBuf,
+    pub tempdir: Option<PathBuf>,
+    pub mountsource: String,
+    /// Propagation mode applied to a partition's root before stacking an
+    /// overlay over it. Defaults to `Propagation::Private`; set
+    /// `propagation=slave`/`shared`/`unbindable` in the global config to opt
+    /// into a different mode.
+    pub propagation: Propagation,
+}
+
+#[derive(Debug, Clone)]
+enum LayerEntry {
+    Set(String, String),
+    Unset(String),
+}
+
+fn global_config_path() -> PathBuf {
+    Path::new(defs::BASE_DIR).join("config.conf")
+}
+
+/// Parses `path` into an ordered list of layer entries, expanding any
+/// `%include <path>` directives in place (relative to the including file)
+/// and recording `%unset <key>` directives. `visited` guards against include
+/// cycles across the whole call chain.
+fn load_layers(path: &Path, visited: &mut HashSet<PathBuf>) -> Vec<LayerEntry> {
+    let mut entries = Vec::new();
+
+    let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if !visited.insert(canonical) {
+        log::warn!("Config include cycle detected at {}, skipping", path.display());
+        return entries;
+    }
+
+    let Ok(content) = fs::read_to_string(path) else {
+        return entries;
+    };
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("%include") {
+            let include_rel = rest.trim();
+            if include_rel.is_empty() {
+                continue;
+            }
+            let include_path = resolve_include(path, include_rel);
+            entries.extend(load_layers(&include_path, visited));
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("%unset") {
+            let key = rest.trim();
+            if !key.is_empty() {
+                entries.push(LayerEntry::Unset(key.to_string()));
+            }
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once('=') {
+            entries.push(LayerEntry::Set(key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    entries
+}
+
+fn resolve_include(including_file: &Path, include_rel: &str) -> PathBuf {
+    let include_path = Path::new(include_rel);
+    if include_path.is_absolute() {
+        return include_path.to_path_buf();
+    }
+    including_file
+        .parent()
+        .map(|dir| dir.join(include_path))
+        .unwrap_or_else(|| include_path.to_path_buf())
+}
+
+/// The effective value of `key` across `layers`: the last `Set` that isn't
+/// later cancelled by an `Unset` of the same key.
+fn effective_value(layers: &[LayerEntry], key: &str) -> Option<String> {
+    let mut value = None;
+    for entry in layers {
+        match entry {
+            LayerEntry::Set(k, v) if k == key => value = Some(v.clone()),
+            LayerEntry::Unset(k) if k == key => value = None,
+            _ => {}
+        }
+    }
+    value
+}
+
+/// Loads the global `propagation` key (`private`/`slave`/`shared`/
+/// `unbindable`), falling back to `Propagation::Private` when the key is
+/// absent or unrecognized.
+pub fn load_propagation() -> Propagation {
+    let mut visited = HashSet::new();
+    let layers = load_layers(&global_config_path(), &mut visited);
+    effective_value(&layers, "propagation")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_default()
+}
+
+/// Loads the merged per-module mount mode (`mode=magic`/`mode=auto`), layering
+/// the global config under each module's own `meta-hybrid.conf` fragment if
+/// it ships one.
+pub fn load_module_modes() -> std::collections::HashMap<String, String> {
+    let mut modes = std::collections::HashMap::new();
+
+    let mut global_visited = HashSet::new();
+    let global_layers = load_layers(&global_config_path(), &mut global_visited);
+
+    let Ok(dirs) = fs::read_dir(defs::MODULE_METADATA_DIR) else {
+        return modes;
+    };
+
+    for entry in dirs.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let id = entry.file_name().to_string_lossy().to_string();
+
+        let mut layers = global_layers.clone();
+        let fragment = path.join("meta-hybrid.conf");
+        if fragment.exists() {
+            let mut visited = HashSet::new();
+            layers.extend(load_layers(&fragment, &mut visited));
+        }
+
+        if let Some(mode) = effective_value(&layers, "mode") {
+            modes.insert(id, mode);
+        }
+    }
+
+    modes
+}
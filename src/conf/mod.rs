@@ -0,0 +1,2 @@
+// src/conf/mod.rs
+pub mod config;
@@ -0,0 +1,102 @@
+// src/core/manifest.rs
+// Per-file content manifest for modules, so sync can detect exactly which
+// files changed instead of re-copying (or blindly trusting) a whole module.
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+const MANIFEST_FILE_NAME: &str = ".hymo-manifest";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileEntry {
+    pub size: u64,
+    pub mtime: i64,
+    pub hash: String,
+}
+
+/// Maps a file's path (relative to the module root) to its recorded size,
+/// mtime, and blake3 content hash.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub files: BTreeMap<String, FileEntry>,
+}
+
+impl Manifest {
+    /// Walks `root` and hashes every regular file under it.
+    pub fn build(root: &Path) -> Result<Manifest> {
+        let mut files = BTreeMap::new();
+        walk(root, root, &mut files)?;
+        Ok(Manifest { files })
+    }
+
+    pub fn load(path: &Path) -> Option<Manifest> {
+        let content = fs::read(path).ok()?;
+        serde_json::from_slice(&content).ok()
+    }
+
+    /// Writes the manifest to `path` (inside the module's directory, see
+    /// `manifest_path`), atomically (write to a temp
+    /// file in the same directory, then rename over the target).
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec(self)?)
+            .with_context(|| format!("writing {}", tmp.display()))?;
+        fs::rename(&tmp, path).with_context(|| format!("renaming manifest into {}", path.display()))
+    }
+
+    /// Paths present in `self` but missing (or changed) relative to `old`.
+    pub fn changed_since(&self, old: &Manifest) -> Vec<PathBuf> {
+        self.files
+            .iter()
+            .filter(|(path, entry)| old.files.get(*path) != Some(*entry))
+            .map(|(path, _)| PathBuf::from(path))
+            .collect()
+    }
+
+    /// Paths present in `old` but no longer present in `self`.
+    pub fn removed_since(&self, old: &Manifest) -> Vec<PathBuf> {
+        old.files
+            .keys()
+            .filter(|path| !self.files.contains_key(*path))
+            .map(PathBuf::from)
+            .collect()
+    }
+}
+
+pub fn manifest_path(module_dir: &Path) -> PathBuf {
+    module_dir.join(MANIFEST_FILE_NAME)
+}
+
+fn walk(root: &Path, current: &Path, files: &mut BTreeMap<String, FileEntry>) -> Result<()> {
+    if !current.is_dir() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(current)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            walk(root, &path, files)?;
+        } else if file_type.is_file() {
+            let relative = path.strip_prefix(root)?.to_string_lossy().to_string();
+            if relative == MANIFEST_FILE_NAME {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            let mtime = metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0);
+            let hash = blake3::hash(&fs::read(&path)?).to_hex().to_string();
+            files.insert(relative, FileEntry { size: metadata.len(), mtime, hash });
+        }
+        // Symlinks and other special files have no stable content to hash;
+        // a full module re-sync on any prop change still covers them.
+    }
+    Ok(())
+}
@@ -2,9 +2,18 @@
 use std::fs;
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
-use anyhow::Result;
+use std::time::{SystemTime, UNIX_EPOCH};
+use anyhow::{Context, Result};
 use serde::Serialize;
-use crate::{conf::config, defs, utils, core::state};
+use crate::{
+    conf::config, defs, utils,
+    core::manifest::{self, Manifest},
+    core::mtime_cache::{self, ModuleCache},
+    core::selinux::{FileContexts, DEFAULT_CONTEXT},
+    core::state::{self, RuntimeState},
+    core::whiteout,
+    mount::loopdev, mount::teardown,
+};
 
 #[derive(Serialize)]
 struct ModuleInfo {
@@ -28,21 +37,43 @@ fn read_prop(path: &Path, key: &str) -> Option<String> {
     None
 }
 
-pub fn update_description(storage_mode: &str, nuke_active: bool, overlay_count: usize, magic_count: usize) {
+pub fn update_description(
+    storage_mode: &str,
+    nuke_active: bool,
+    overlay_count: usize,
+    magic_count: usize,
+    forced_magic_partitions: &[String],
+    conflict_count: usize,
+) {
     let path = Path::new(defs::MODULE_PROP_FILE);
-    if !path.exists() { 
+    if !path.exists() {
         log::warn!("module.prop not found at {}, skipping description update", path.display());
-        return; 
+        return;
     }
 
     let mode_str = if storage_mode == "tmpfs" { "Tmpfs" } else { "Ext4" };
     let status_emoji = if storage_mode == "tmpfs" { "🐾" } else { "💿" };
-    
+
     let nuke_str = if nuke_active { " | 肉垫: 开启 ✨" } else { "" };
-    
+
+    // Surface *why* a partition landed on Magic Mount when it wasn't the
+    // module's own choice, instead of just showing the tmpfs/ext4 storage
+    // emoji with no explanation.
+    let fs_forced_str = if forced_magic_partitions.is_empty() {
+        String::new()
+    } else {
+        format!(" | FS-forced: {}", forced_magic_partitions.join(","))
+    };
+
+    let conflicts_str = if conflict_count > 0 {
+        format!(" | Conflicts: {}", conflict_count)
+    } else {
+        String::new()
+    };
+
     let new_desc = format!(
-        "description=😋 运行中喵～ ({}) {} | Overlay: {} | Magic: {}{}", 
-        mode_str, status_emoji, overlay_count, magic_count, nuke_str
+        "description=😋 运行中喵～ ({}) {} | Overlay: {} | Magic: {}{}{}{}",
+        mode_str, status_emoji, overlay_count, magic_count, nuke_str, fs_forced_str, conflicts_str
     );
 
     let mut new_lines = Vec::new();
@@ -80,33 +111,171 @@ pub fn scan_enabled_ids(metadata_dir: &Path) -> Result<Vec<String>> {
     Ok(ids)
 }
 
-/// Recursively fix SELinux contexts of a module by mirroring from the real system.
-fn repair_contexts(module_root: &Path, current_path: &Path) -> Result<()> {
-    if !current_path.exists() { return Ok(()); }
+/// Mounts the loop-backed ext4 storage image at `target_base`, unless it's
+/// already mounted there. A plain `statfs` type check can't tell "the image
+/// is mounted here" from "`target_base` just happens to sit on a host
+/// partition that's already ext4" (common on Android, where `/data` itself
+/// is frequently ext4) — it would report a false positive and skip mounting
+/// the image entirely, silently losing isolation. Comparing `st_dev` against
+/// the parent directory is the same test the kernel itself uses to decide
+/// whether a path is a mount point.
+fn ensure_storage_mounted(target_base: &Path) -> Result<()> {
+    let state = RuntimeState::load().with_context(|| "loading runtime state")?;
+    if state.storage_mode != "ext4" { return Ok(()); }
+
+    if is_mount_point(target_base) {
+        log::debug!("Ext4 storage image already mounted at {}", target_base.display());
+        return Ok(());
+    }
+
+    log::info!("Mounting ext4 storage image at {}", target_base.display());
+    loopdev::mount_ext4_image(Path::new(defs::STORAGE_IMAGE_PATH), target_base, defs::STORAGE_IMAGE_SIZE)
+        .map(|_loop_dev| ())
+}
+
+/// True if `path` is itself a distinct mount point, i.e. its `st_dev` differs
+/// from its parent directory's. Returns `false` (rather than erroring) for a
+/// path that doesn't exist yet, or whose parent can't be stat'd — both cases
+/// mean "nothing is mounted there", which is what callers want.
+fn is_mount_point(path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    let Some(parent) = path.parent() else { return false };
+    let (Ok(path_meta), Ok(parent_meta)) = (fs::metadata(path), fs::metadata(parent)) else { return false };
+    path_meta.dev() != parent_meta.dev()
+}
+
+/// Recursively fixes SELinux contexts of a module's synced files. A file
+/// that also exists on the real system is mirrored from there; one that
+/// doesn't (module-added, with no live counterpart to copy from) is labeled
+/// from the device's file_contexts database instead of being left with
+/// whatever context it happened to be copied with.
+fn repair_contexts(file_contexts: &FileContexts, module_root: &Path, current_path: &Path) -> Result<()> {
+    if !current_path.exists() || whiteout::is_whiteout(current_path) { return Ok(()); }
     let relative = current_path.strip_prefix(module_root)?;
     let system_path = Path::new("/").join(relative);
+
     if system_path.exists() {
         if let Err(e) = utils::copy_path_context(&system_path, current_path) {
             log::debug!("Failed to mirror context for {}: {}", relative.display(), e);
         }
-    } else {
+    } else if let Some(ctx) = file_contexts.label_for(&system_path) {
+        if let Err(e) = utils::set_path_context(current_path, &ctx) {
+            log::debug!("Failed to label {} as {}: {}", relative.display(), ctx, e);
+        }
+    } else if let Err(e) = utils::set_path_context(current_path, DEFAULT_CONTEXT) {
+        log::debug!("Failed to apply default context to {}: {}", relative.display(), e);
     }
 
     if current_path.is_dir() {
         for entry in fs::read_dir(current_path)? {
             let entry = entry?;
-            repair_contexts(module_root, &entry.path())?;
+            repair_contexts(file_contexts, module_root, &entry.path())?;
         }
     }
     Ok(())
 }
 
-pub fn sync_active(source_dir: &Path, target_base: &Path) -> Result<()> {
-    log::info!("Syncing modules from {} to {}", source_dir.display(), target_base.display());
-    let ids = scan_enabled_ids(source_dir)?;
-    log::debug!("Found {} enabled modules to sync.", ids.len());
-    
-    // 1. Prune stale modules from storage
+/// Fully re-copies a module's content into its deployed copy with
+/// `utils::sync_dir` (the deployed copy is not removed first), then rebuilds
+/// its manifest from scratch. Used whenever incremental diffing can't be
+/// trusted: the module's own files changed in a way the cache doesn't track
+/// (prop change), or there was no prior manifest to diff against at all.
+fn full_resync(id: &str, src: &Path, dst: &Path, manifest_path: &Path, file_contexts: &FileContexts) -> Result<()> {
+    log::info!("Full resync for module {}", id);
+    utils::sync_dir(src, dst).with_context(|| format!("syncing module {id}"))?;
+    for part in defs::BUILTIN_PARTITIONS {
+        let part_root = dst.join(part);
+        if part_root.exists() {
+            if let Err(e) = repair_contexts(file_contexts, dst, &part_root) {
+                log::warn!("Context repair failed for {}/{}: {}", id, part, e);
+            }
+        }
+    }
+    let new_manifest = Manifest::build(dst)?;
+    new_manifest.save(manifest_path)?;
+    Ok(())
+}
+
+/// Syncs a single module's content from `src` into `dst`, diffing against
+/// both the last recorded manifest AND the manifest of what's actually
+/// deployed at `dst` — so corruption of the deployed copy (not just a
+/// source-side change) is caught and repaired instead of silently trusted.
+fn sync_module(id: &str, src: &Path, dst: &Path, file_contexts: &FileContexts) -> Result<()> {
+    let manifest_path = manifest::manifest_path(dst);
+
+    if prop_changed(src, dst) || !dst.exists() {
+        return full_resync(id, src, dst, &manifest_path, file_contexts);
+    }
+
+    let Some(old_manifest) = Manifest::load(&manifest_path) else {
+        return full_resync(id, src, dst, &manifest_path, file_contexts);
+    };
+
+    let new_manifest = Manifest::build(src)?;
+    let deployed_manifest = Manifest::build(dst)?;
+
+    let mut changed: std::collections::HashSet<PathBuf> = new_manifest.changed_since(&old_manifest).into_iter().collect();
+    // The deployed copy diverging from what we last recorded (disk
+    // corruption, a stray write from elsewhere) is just as much a reason to
+    // re-copy a file as the source having changed. Only size and content
+    // count here: `fs::copy` gives the deployed file a fresh mtime, so an
+    // mtime difference alone is not divergence.
+    changed.extend(
+        deployed_manifest
+            .files
+            .iter()
+            .filter(|(path, entry)| {
+                old_manifest.files.get(*path).map_or(true, |old| old.size != entry.size || old.hash != entry.hash)
+            })
+            .map(|(path, _)| PathBuf::from(path)),
+    );
+
+    let removed = new_manifest.removed_since(&old_manifest);
+
+    log::debug!("Syncing module {}: {} changed, {} removed", id, changed.len(), removed.len());
+
+    for rel in &removed {
+        let target = dst.join(rel);
+        if let Err(e) = fs::remove_file(&target) {
+            log::warn!("Failed to remove {}: {}", target.display(), e);
+        }
+    }
+
+    for rel in &changed {
+        let from = src.join(rel);
+        let to = dst.join(rel);
+        if !from.exists() {
+            continue;
+        }
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(&from, &to).with_context(|| format!("copying {}", rel.display()))?;
+        if let Err(e) = repair_contexts(file_contexts, dst, &to) {
+            log::debug!("Context repair failed for {}: {}", to.display(), e);
+        }
+    }
+
+    new_manifest.save(&manifest_path)?;
+    Ok(())
+}
+
+fn prop_changed(src: &Path, dst: &Path) -> bool {
+    let src_prop = src.join("module.prop");
+    let dst_prop = dst.join("module.prop");
+    match (fs::read(&src_prop), fs::read(&dst_prop)) {
+        (Ok(a), Ok(b)) => a != b,
+        _ => true,
+    }
+}
+
+/// Removes any module directory under `target_base` that's no longer in the
+/// enabled set, then — if anything was actually pruned — tears down every
+/// mount we manage over the builtin partitions, since a pruned module's
+/// content may still be referenced by a live overlay lowerdir stack. Returns
+/// whether a teardown happened, so the caller knows mounts need re-running.
+fn prune_orphaned_modules(ids: &[String], target_base: &Path) -> Result<bool> {
+    let mut pruned_any = false;
     if target_base.exists() {
         for entry in fs::read_dir(target_base)? {
             let entry = entry?;
@@ -119,36 +288,104 @@ pub fn sync_active(source_dir: &Path, target_base: &Path) -> Result<()> {
                 log::info!("Pruning stale/disabled module from storage: {}", id);
                 if let Err(e) = fs::remove_dir_all(&path) {
                     log::warn!("Failed to remove stale module {}: {}", id, e);
+                } else {
+                    pruned_any = true;
                 }
             }
         }
     }
 
-    // 2. Sync enabled modules
+    if pruned_any {
+        let partition_roots: Vec<&Path> = defs::BUILTIN_PARTITIONS.iter().map(Path::new).collect();
+        teardown::teardown(&partition_roots)?;
+    }
+
+    Ok(pruned_any)
+}
+
+fn has_files_recursive(path: &Path) -> bool {
+    let Ok(entries) = fs::read_dir(path) else { return false };
+    for entry in entries.flatten() {
+        let p = entry.path();
+        if p.is_dir() {
+            if has_files_recursive(&p) { return true; }
+        } else {
+            return true;
+        }
+    }
+    false
+}
+
+/// Syncs every enabled module's content from `source_dir` into `target_base`.
+/// Mounts the ext4 storage image first if configured for it, prunes modules
+/// that are no longer enabled, then for each remaining module: skips it
+/// outright if its mtime cache still matches (cheap, the common case on a
+/// warm boot), otherwise does a blake3 manifest diff against both the last
+/// sync and the currently-deployed copy (to catch corruption, not just
+/// source-side changes) and relabels SELinux contexts on whatever actually
+/// changed. Returns whether pruning tore down live partition mounts, so the
+/// caller knows it must re-run the mount orchestrator before this is usable.
+pub fn sync_active(source_dir: &Path, target_base: &Path) -> Result<bool> {
+    log::info!("Syncing modules from {} to {}", source_dir.display(), target_base.display());
+
+    ensure_storage_mounted(target_base)?;
+
+    let ids = scan_enabled_ids(source_dir)?;
+    log::debug!("Found {} enabled modules to sync.", ids.len());
+
+    let needs_remount = prune_orphaned_modules(&ids, target_base)?;
+
+    let sync_started_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+
+    let file_contexts = FileContexts::load();
+
     for id in ids {
         let src = source_dir.join(&id);
         let dst = target_base.join(&id);
-        let has_content = defs::BUILTIN_PARTITIONS.iter().any(|p| src.join(p).exists());
-        
-        if has_content {
-            log::debug!("Syncing module: {}", id);
-            if let Err(e) = utils::sync_dir(&src, &dst) {
-                log::error!("Failed to sync module {}: {}", id, e);
-            } else {
-                // 3. Context Mirroring Pass
-                log::debug!("Repairing SELinux contexts for {}", id);
-                for part in defs::BUILTIN_PARTITIONS {
-                    let part_root = dst.join(part);
-                    if part_root.exists() {
-                        if let Err(e) = repair_contexts(&dst, &part_root) {
-                            log::warn!("Context repair failed for {}/{}: {}", id, part, e);
-                        }
-                    }
-                }
+        let has_content = defs::BUILTIN_PARTITIONS.iter().any(|p| has_files_recursive(&src.join(p)));
+
+        if !has_content {
+            log::debug!("Skipping empty module: {}", id);
+            continue;
+        }
+
+        let new_cache = match mtime_cache::build(&src, sync_started_at) {
+            Ok(c) => c,
+            Err(e) => {
+                log::warn!("Failed to build sync cache for {}: {}, forcing sync", id, e);
+                ModuleCache::default()
             }
+        };
+
+        let up_to_date = dst.exists()
+            && ModuleCache::load(&id)
+                .map(|old| mtime_cache::unchanged(&old, &new_cache))
+                .unwrap_or(false);
+
+        if up_to_date {
+            log::debug!("Skipping module: {} (cache hit)", id);
+            continue;
+        }
+
+        log::debug!("Syncing module: {}", id);
+        if let Err(e) = sync_module(&id, &src, &dst, &file_contexts) {
+            log::error!("Failed to sync module {}: {}", id, e);
+            continue;
+        }
+
+        if let Err(e) = new_cache.save(&id) {
+            log::warn!("Failed to save sync cache for {}: {}", id, e);
         }
     }
-    Ok(())
+
+    if needs_remount {
+        log::warn!("Storage sync tore down live partition mounts while pruning orphaned modules; the caller must re-run the mount orchestrator before this is usable again");
+    }
+
+    Ok(needs_remount)
 }
 
 pub fn print_list(config: &config::Config) -> Result<()> {
@@ -156,8 +393,13 @@ pub fn print_list(config: &config::Config) -> Result<()> {
     let modules_dir = &config.moduledir;
     let mut modules = Vec::new();
 
-    let state = state::RuntimeState::load().unwrap_or_default();
-    
+    // Unlike a missing file (first run, treated as defaults inside `load`),
+    // a state file that fails to parse is real corruption — `mount_point`
+    // below is load-bearing for locating module content, so surface it
+    // instead of silently falling back to defaults.
+    let state = state::RuntimeState::load()
+        .with_context(|| "loading runtime state")?;
+
     let mut mnt_base = PathBuf::from(defs::FALLBACK_CONTENT_DIR);
     if !state.mount_point.as_os_str().is_empty() {
         mnt_base = state.mount_point;
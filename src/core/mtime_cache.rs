@@ -0,0 +1,103 @@
+// src/core/mtime_cache.rs
+// Per-module sync manifest cache for modules::sync_active: keyed on file
+// size + mtime (second resolution) rather than content hashing, so the
+// every-boot re-sync `sync_active` used to do unconditionally stays cheap.
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::defs;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub size: u64,
+    pub mtime: i64,
+    /// True if, at the time this entry was recorded, its mtime was at or
+    /// after the wall-clock second the sync pass started in. A file can be rewritten again
+    /// within that same second without its mtime changing, so an ambiguous
+    /// entry can never be trusted as "unchanged" — dirstate-v2 calls this a
+    /// race condition on mtime resolution.
+    pub needs_recheck: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ModuleCache {
+    pub files: BTreeMap<String, Entry>,
+}
+
+fn cache_path(module_id: &str) -> PathBuf {
+    Path::new(defs::BASE_DIR).join("sync-cache").join(format!("{module_id}.json"))
+}
+
+impl ModuleCache {
+    pub fn load(module_id: &str) -> Option<ModuleCache> {
+        let content = fs::read(cache_path(module_id)).ok()?;
+        serde_json::from_slice(&content).ok()
+    }
+
+    /// Rewrites the cache atomically: write to a temp file in the same
+    /// directory, then rename over the target.
+    pub fn save(&self, module_id: &str) -> Result<()> {
+        let path = cache_path(module_id);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec(self)?)
+            .with_context(|| format!("writing {}", tmp.display()))?;
+        fs::rename(&tmp, &path).with_context(|| format!("renaming cache into {}", path.display()))
+    }
+}
+
+/// Walks `root`, recording size + mtime (second resolution) for every file.
+/// `sync_started_at` is the wall-clock second this sync pass began.
+pub fn build(root: &Path, sync_started_at: i64) -> Result<ModuleCache> {
+    let mut files = BTreeMap::new();
+    walk(root, root, sync_started_at, &mut files)?;
+    Ok(ModuleCache { files })
+}
+
+fn walk(root: &Path, current: &Path, sync_started_at: i64, files: &mut BTreeMap<String, Entry>) -> Result<()> {
+    if !current.is_dir() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(current)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            walk(root, &path, sync_started_at, files)?;
+        } else {
+            let relative = path.strip_prefix(root)?.to_string_lossy().to_string();
+            let metadata = entry.metadata()?;
+            let mtime = metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0);
+            let needs_recheck = mtime >= sync_started_at;
+            files.insert(relative, Entry { size: metadata.len(), mtime, needs_recheck });
+        }
+    }
+    Ok(())
+}
+
+/// True if `new` exactly matches `old`: same file set, same sizes, same
+/// mtimes, and neither carries an unresolved ambiguous-mtime flag.
+pub fn unchanged(old: &ModuleCache, new: &ModuleCache) -> bool {
+    if old.files.len() != new.files.len() {
+        return false;
+    }
+    old.files.iter().all(|(path, old_entry)| {
+        new.files.get(path).is_some_and(|new_entry| {
+            !old_entry.needs_recheck
+                && !new_entry.needs_recheck
+                && old_entry.size == new_entry.size
+                && old_entry.mtime == new_entry.mtime
+        })
+    })
+}
@@ -1,9 +1,10 @@
 // src/core/planner.rs
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use anyhow::Result;
-use crate::{conf::config, defs, core::inventory::Module};
+use serde::Serialize;
+use crate::{conf::config, defs, core::inventory::Module, mount::fsprobe};
 
 #[derive(Debug)]
 pub struct OverlayOperation {
@@ -12,14 +13,43 @@ pub struct OverlayOperation {
     pub lowerdirs: Vec<PathBuf>,
 }
 
+/// A file contributed by more than one module to the same partition. Module
+/// authors and the WebUI use this to see who actually won, instead of
+/// diffing mounted trees by hand.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileConflict {
+    pub partition: String,
+    pub relative_path: String,
+    pub winner_id: String,
+    pub shadowed_ids: Vec<String>,
+}
+
 #[derive(Debug, Default)]
 pub struct MountPlan {
     pub overlay_ops: Vec<OverlayOperation>,
     pub magic_module_paths: Vec<PathBuf>,
-    
+
     // For stats and reporting
     pub overlay_module_ids: Vec<String>,
     pub magic_module_ids: Vec<String>,
+
+    // Detected backing filesystem per partition (e.g. "system" -> "f2fs"),
+    // so `update_description`/`print_list` can explain why a partition was
+    // routed to a particular backend instead of showing a guessed emoji.
+    pub partition_filesystems: HashMap<String, String>,
+    // Partitions forced to Magic Mount because their backing store is
+    // known-problematic for a second OverlayFS (an existing overlay mount,
+    // NFS, etc), rather than by per-module config.
+    pub forced_magic_partitions: Vec<String>,
+    // Files shadowed when more than one module provides the same
+    // partition-relative path in the OverlayFS lowerdir stack.
+    pub file_conflicts: Vec<FileConflict>,
+}
+
+/// Renders `plan`'s conflicts as JSON, for a CLI subcommand or the WebUI to
+/// consume directly.
+pub fn conflicts_report(plan: &MountPlan) -> Result<String> {
+    Ok(serde_json::to_string(&plan.file_conflicts)?)
 }
 
 /// Generates a mount plan based on the inventory and current storage state.
@@ -34,15 +64,35 @@ pub fn generate(
     let mut magic_paths = HashSet::new();
     let mut overlay_ids = HashSet::new();
     let mut magic_ids = HashSet::new();
+    // partition -> relative file path -> contributing module ids, in the
+    // same Z->A priority order the lowerdir stack is built in (first = wins).
+    let mut partition_providers: HashMap<String, BTreeMap<String, Vec<String>>> = HashMap::new();
 
     // Partitions to consider for OverlayFS
     let mut target_partitions = defs::BUILTIN_PARTITIONS.to_vec();
     target_partitions.extend(config.partitions.iter().map(|s| s.as_str()));
 
+    // Proactively probe each target partition's backing filesystem, rather
+    // than only discovering a bad fit reactively once `mount_overlay` fails.
+    let mut partition_filesystems = HashMap::new();
+    let mut forced_magic: HashSet<&str> = HashSet::new();
+    for &part in &target_partitions {
+        if let Some(fs_kind) = fsprobe::detect(Path::new(&format!("/{part}"))) {
+            partition_filesystems.insert(part.to_string(), fs_kind.label());
+            if fs_kind.overlay_unsafe() {
+                log::info!("/{part} is backed by {}, forcing Magic Mount for it", fs_kind.label());
+                forced_magic.insert(part);
+            }
+        }
+    }
+    if let Some(fs_kind) = fsprobe::detect(storage_root) {
+        log::debug!("Storage root {} is backed by {}", storage_root.display(), fs_kind.label());
+    }
+
     // Modules are already sorted Z->A in inventory.
     for module in modules {
         let content_path = storage_root.join(&module.id);
-        
+
         if !content_path.exists() {
             log::debug!("Planner: Module {} content missing (sync failed?), skipping", module.id);
             continue;
@@ -54,25 +104,49 @@ pub fn generate(
                 magic_ids.insert(module.id.clone());
             }
         } else {
-            // Try OverlayFS ("auto" mode)
-            let mut participates_in_overlay = false;
+            // Try OverlayFS ("auto" mode), unless ANY of the module's
+            // partitions was probed as unsafe for a second OverlayFS — a
+            // module goes to Magic Mount as a whole, the same way "mode"
+            // above is whole-module. Deciding this requires seeing every
+            // partition the module touches before committing any of them to
+            // `partition_layers`/`partition_providers`: otherwise a partition
+            // seen before the forcing one would end up mounted twice, once
+            // as an overlay lowerdir and again via Magic Mount's whole-tree
+            // walk.
+            let mut candidate_parts: Vec<(&str, PathBuf)> = Vec::new();
+            let mut forced_to_magic = false;
 
-            for part in &target_partitions {
+            for &part in &target_partitions {
                 let part_path = content_path.join(part);
                 if part_path.is_dir() && has_files(&part_path) {
+                    if forced_magic.contains(part) {
+                        forced_to_magic = true;
+                    }
+                    candidate_parts.push((part, part_path));
+                }
+            }
+
+            if forced_to_magic {
+                magic_paths.insert(content_path);
+                magic_ids.insert(module.id.clone());
+            } else if !candidate_parts.is_empty() {
+                for (part, part_path) in candidate_parts {
+                    for relative in collect_relative_files(&part_path) {
+                        partition_providers
+                            .entry(part.to_string())
+                            .or_default()
+                            .entry(relative.to_string_lossy().to_string())
+                            .or_default()
+                            .push(module.id.clone());
+                    }
+
                     partition_layers.entry(part.to_string())
                         .or_default()
                         .push(part_path);
-                    participates_in_overlay = true;
                 }
-            }
-
-            if participates_in_overlay {
                 overlay_ids.insert(module.id.clone());
-            } else {
-                if has_meaningful_content(&content_path, &target_partitions) {
-                     // Fallback logic could go here
-                }
+            } else if has_meaningful_content(&content_path, &target_partitions) {
+                // Fallback logic could go here
             }
         }
     }
@@ -84,16 +158,65 @@ pub fn generate(
         });
     }
 
+    // `partition_providers` is only ever populated for modules that land
+    // fully in the overlay stack (see the pre-pass above), so every id here
+    // should already be in `overlay_ids`. Filter defensively anyway: a
+    // conflict naming a module that was actually routed to Magic Mount would
+    // be worse than useless — it'd point at a "winner" that was never part
+    // of the overlay lowerdir at all.
+    let mut file_conflicts: Vec<FileConflict> = Vec::new();
+    for (part, providers) in &partition_providers {
+        for (relative_path, ids) in providers {
+            let overlay_ids_only: Vec<String> = ids
+                .iter()
+                .filter(|id| overlay_ids.contains(*id))
+                .cloned()
+                .collect();
+            if overlay_ids_only.len() > 1 {
+                file_conflicts.push(FileConflict {
+                    partition: part.clone(),
+                    relative_path: relative_path.clone(),
+                    winner_id: overlay_ids_only[0].clone(),
+                    shadowed_ids: overlay_ids_only[1..].to_vec(),
+                });
+            }
+        }
+    }
+    file_conflicts.sort_by(|a, b| (&a.partition, &a.relative_path).cmp(&(&b.partition, &b.relative_path)));
+
     plan.magic_module_paths = magic_paths.into_iter().collect();
     plan.overlay_module_ids = overlay_ids.into_iter().collect();
     plan.magic_module_ids = magic_ids.into_iter().collect();
+    plan.partition_filesystems = partition_filesystems;
+    plan.forced_magic_partitions = forced_magic.into_iter().map(str::to_string).collect();
+    plan.file_conflicts = file_conflicts;
 
     plan.overlay_module_ids.sort();
     plan.magic_module_ids.sort();
+    plan.forced_magic_partitions.sort();
 
     Ok(plan)
 }
 
+/// Recursively lists every regular file under `base`, relative to `base`.
+fn collect_relative_files(base: &Path) -> Vec<PathBuf> {
+    let mut out = Vec::new();
+    collect_relative_files_into(base, base, &mut out);
+    out
+}
+
+fn collect_relative_files_into(base: &Path, current: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(current) else { return };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_relative_files_into(base, &path, out);
+        } else if let Ok(relative) = path.strip_prefix(base) {
+            out.push(relative.to_path_buf());
+        }
+    }
+}
+
 fn has_files(path: &Path) -> bool {
     if let Ok(entries) = fs::read_dir(path) {
         for _ in entries.flatten() {
@@ -0,0 +1,91 @@
+// src/core/state.rs
+// Versioned on-disk format for RuntimeState, modeled on dirstate-v2: a fixed
+// magic marker followed by a version field so the format can evolve without
+// silently losing state on every upgrade.
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::defs;
+
+const MAGIC: &[u8; 12] = b"hymo-state1\n";
+const CURRENT_VERSION: u32 = 1;
+
+fn state_path() -> PathBuf {
+    Path::new(defs::BASE_DIR).join("state.bin")
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct RuntimeState {
+    pub mount_point: PathBuf,
+    pub storage_mode: String,
+    pub nuke_active: bool,
+}
+
+impl RuntimeState {
+    /// Loads the runtime state, verifying the magic marker and dispatching
+    /// parsing by version. A file with no marker is assumed to be the
+    /// pre-versioning legacy format (bare JSON) and is transparently
+    /// migrated up to `CURRENT_VERSION`, rewriting it so the next load skips
+    /// the migration path. A marker that names an unknown version, or a body
+    /// that fails to parse, is a genuine corruption and is reported as an
+    /// error rather than silently discarded — callers like `print_list` rely
+    /// on `mount_point` actually being right.
+    pub fn load() -> Result<RuntimeState> {
+        let path = state_path();
+        let bytes = match fs::read(&path) {
+            Ok(b) => b,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(RuntimeState::default()),
+            Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+        };
+
+        if bytes.len() >= MAGIC.len() && bytes[..MAGIC.len()] == *MAGIC {
+            let version_bytes = bytes
+                .get(MAGIC.len()..MAGIC.len() + 4)
+                .with_context(|| format!("{} is truncated after the magic marker", path.display()))?;
+            let version = u32::from_le_bytes(version_bytes.try_into().unwrap());
+            let body = &bytes[MAGIC.len() + 4..];
+            return Self::parse_versioned(version, body)
+                .with_context(|| format!("parsing {} (version {})", path.display(), version));
+        }
+
+        // No marker: either the legacy bare-JSON format, or genuine garbage.
+        match serde_json::from_slice::<RuntimeState>(&bytes) {
+            Ok(state) => {
+                log::info!("Migrating legacy state file {} to versioned format", path.display());
+                if let Err(e) = state.save() {
+                    log::warn!("Failed to rewrite migrated state file: {}", e);
+                }
+                Ok(state)
+            }
+            Err(e) => bail!("{} is neither a versioned nor a legacy state file: {}", path.display(), e),
+        }
+    }
+
+    fn parse_versioned(version: u32, body: &[u8]) -> Result<RuntimeState> {
+        match version {
+            1 => serde_json::from_slice(body).with_context(|| "parsing v1 state body"),
+            other => bail!("unsupported state file version {other}"),
+        }
+    }
+
+    /// Writes the state back out with the current magic marker and version,
+    /// atomically (write to a temp file in the same directory, then rename
+    /// over the target).
+    pub fn save(&self) -> Result<()> {
+        let path = state_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut bytes = Vec::with_capacity(MAGIC.len() + 4 + 64);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&CURRENT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&serde_json::to_vec(self)?);
+
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, &bytes).with_context(|| format!("writing {}", tmp.display()))?;
+        fs::rename(&tmp, &path).with_context(|| format!("renaming state into {}", path.display()))
+    }
+}
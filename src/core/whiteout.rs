@@ -0,0 +1,135 @@
+// src/core/whiteout.rs
+// Gives modules true deletion semantics over the stock tree, matching the
+// kernel overlayfs whiteout/opaque-directory rules.
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use rustix::fs::{mknodat, setxattr, FileType, Mode, XattrFlags, CWD};
+
+/// Per-module file listing partition-relative paths to delete, one per line,
+/// e.g. `system/app/Bloat/Bloat.apk`. Lives next to the module's `module.prop`.
+pub const REMOVALS_LIST_FILE: &str = "REMOVE";
+
+/// Marker file that, when present in a module directory, makes that directory
+/// opaque: everything beneath it in the stock tree is fully shadowed rather
+/// than merged.
+pub const OPAQUE_MARKER_FILE: &str = ".replace";
+
+/// A single pending overlay deletion, relative to a partition root (e.g. `system`).
+#[derive(Debug, Clone)]
+pub enum Whiteout {
+    /// Delete a single file/symlink/empty dir.
+    File(PathBuf),
+    /// Shadow an entire directory subtree.
+    OpaqueDir(PathBuf),
+}
+
+/// Reads a module's `REMOVE` list plus any `.replace` opaque markers found
+/// under its partition trees.
+pub fn collect_whiteouts(module_root: &Path, partitions: &[&str]) -> Vec<Whiteout> {
+    let mut out = Vec::new();
+
+    let list_path = module_root.join(REMOVALS_LIST_FILE);
+    if let Ok(content) = fs::read_to_string(&list_path) {
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            out.push(Whiteout::File(PathBuf::from(line)));
+        }
+    }
+
+    for part in partitions {
+        let part_root = module_root.join(part);
+        scan_opaque_markers(&part_root, Path::new(part), &mut out);
+    }
+
+    out
+}
+
+/// Narrows a module's full whiteout list (paths like `system/app/X.apk`,
+/// spanning every partition) down to the ones for a single `partition`, with
+/// that partition's name stripped so the paths are relative to the partition
+/// root `apply_whiteouts`'s upperdir expects.
+pub fn for_partition(whiteouts: &[Whiteout], partition: &str) -> Vec<Whiteout> {
+    let prefix = Path::new(partition);
+    whiteouts
+        .iter()
+        .filter_map(|w| match w {
+            Whiteout::File(rel) => rel
+                .strip_prefix(prefix)
+                .ok()
+                .map(|r| Whiteout::File(r.to_path_buf())),
+            Whiteout::OpaqueDir(rel) => rel
+                .strip_prefix(prefix)
+                .ok()
+                .map(|r| Whiteout::OpaqueDir(r.to_path_buf())),
+        })
+        .collect()
+}
+
+fn scan_opaque_markers(dir: &Path, relative: &Path, out: &mut Vec<Whiteout>) {
+    if !dir.is_dir() {
+        return;
+    }
+    if dir.join(OPAQUE_MARKER_FILE).exists() {
+        out.push(Whiteout::OpaqueDir(relative.to_path_buf()));
+        return; // shadowing the whole subtree, no need to recurse further
+    }
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            if entry.path().is_dir() {
+                scan_opaque_markers(&entry.path(), &relative.join(entry.file_name()), out);
+            }
+        }
+    }
+}
+
+/// Realizes a set of whiteouts inside an overlayfs upperdir: a `c 0 0` node
+/// for a plain deletion, and the `trusted.overlay.opaque=y` xattr for a
+/// directory that should fully shadow the lower layers.
+pub fn apply_whiteouts(upperdir: &Path, whiteouts: &[Whiteout]) -> Result<()> {
+    for w in whiteouts {
+        match w {
+            Whiteout::File(rel) => {
+                let target = upperdir.join(rel);
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)
+                        .with_context(|| format!("creating upperdir parent for whiteout {}", rel.display()))?;
+                }
+                if target.exists() || target.is_symlink() {
+                    fs::remove_file(&target)
+                        .or_else(|_| fs::remove_dir_all(&target))
+                        .ok();
+                }
+                // Device number 0 is the kernel's whiteout sentinel.
+                mknodat(CWD, &target, FileType::CharacterDevice, Mode::from_raw_mode(0o644), 0)
+                    .with_context(|| format!("mknod whiteout at {}", target.display()))?;
+                log::info!("Whiteout created for {}", rel.display());
+            }
+            Whiteout::OpaqueDir(rel) => {
+                let target = upperdir.join(rel);
+                fs::create_dir_all(&target)
+                    .with_context(|| format!("creating opaque dir {}", rel.display()))?;
+                setxattr(&target, "trusted.overlay.opaque", b"y", XattrFlags::empty())
+                    .with_context(|| format!("setting opaque xattr on {}", target.display()))?;
+                log::info!("{} marked opaque (module replaces directory)", rel.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// True if `path` is itself an overlayfs whiteout node (a `c 0 0` character
+/// device). Context-repair and sync passes must leave these alone.
+pub fn is_whiteout(path: &Path) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    match fs::symlink_metadata(path) {
+        Ok(meta) => {
+            use std::os::unix::fs::MetadataExt;
+            meta.file_type().is_char_device() && meta.rdev() == 0
+        }
+        Err(_) => false,
+    }
+}
@@ -29,4 +29,9 @@ pub const SYSTEM_RW_DIR: &str = "/data/adb/meta-hybrid/rw";
 
 // LKM Paths
 // This points to where the kernel modules are installed in the Magisk/KSU module directory.
-pub const MODULE_LKM_DIR: &str = "/data/adb/modules/meta-hybrid/lkm/binaries";
\ No newline at end of file
+pub const MODULE_LKM_DIR: &str = "/data/adb/modules/meta-hybrid/lkm/binaries";
+
+// Loop-backed ext4 image storage backend (see mount::loopdev)
+pub const STORAGE_IMAGE_PATH: &str = "/data/adb/meta-hybrid/storage.img";
+// 512 MiB default image size; sparse, so actual disk usage tracks real content.
+pub const STORAGE_IMAGE_SIZE: u64 = 512 * 1024 * 1024;
\ No newline at end of file
@@ -2,8 +2,10 @@ use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use anyhow::Result;
 
-use crate::config::Config;
+use crate::conf::config::{self, Config};
+use crate::core::whiteout::{self, Whiteout};
 use crate::magic_mount;
+use crate::mount::fsprobe;
 use crate::overlay_mount;
 use crate::utils;
 
@@ -14,10 +16,11 @@ const BUILTIN_PARTITIONS: &[&str] = &[
 
 pub fn run(active_modules: HashMap<String, PathBuf>, config: &Config) -> Result<()> {
     // 1. Load Module Modes
-    let module_modes = crate::config::load_module_modes();
+    let module_modes = config::load_module_modes();
 
     // 2. Group by Partition
     let mut partition_map: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    let mut partition_whiteouts: HashMap<String, Vec<Whiteout>> = HashMap::new();
     let mut magic_force_map: HashMap<String, bool> = HashMap::new();
 
     let mut all_partitions = BUILTIN_PARTITIONS.to_vec();
@@ -26,6 +29,24 @@ pub fn run(active_modules: HashMap<String, PathBuf>, config: &Config) -> Result<
     let mut sorted_modules: Vec<_> = active_modules.into_iter().collect();
     sorted_modules.sort_by(|a, b| a.0.cmp(&b.0));
 
+    // Proactively probe each partition's backing filesystem before deciding
+    // any module's mount mode, instead of only discovering a bad fit once
+    // `mount_overlay` has already failed.
+    let mut fs_forced_partitions: HashSet<String> = HashSet::new();
+    for &part in &all_partitions {
+        if let Some(fs_kind) = fsprobe::detect(std::path::Path::new(&format!("/{part}"))) {
+            if fs_kind.overlay_unsafe() {
+                tracing::info!(
+                    "/{} is backed by {}, forcing Magic Mount for it",
+                    part, fs_kind.label()
+                );
+                fs_forced_partitions.insert(part.to_string());
+            }
+        }
+    }
+
+    let mut magic_modules: HashSet<PathBuf> = HashSet::new();
+
     for (module_id, content_path) in &sorted_modules {
         if !content_path.exists() {
             tracing::debug!("Module {} content missing at {}", module_id, content_path.display());
@@ -35,17 +56,46 @@ pub fn run(active_modules: HashMap<String, PathBuf>, config: &Config) -> Result<
         let mode = module_modes.get(module_id).map(|s| s.as_str()).unwrap_or("auto");
         let is_magic = mode == "magic";
 
+        // Collected once per module: a module's REMOVE list / .replace
+        // markers span every partition it touches.
+        let module_whiteouts = whiteout::collect_whiteouts(content_path, &all_partitions);
+
+        // Decide Magic-Mount-vs-Overlay for the WHOLE module before
+        // committing any of its partitions to `partition_map`: a module
+        // forced to Magic Mount on one partition (e.g. f2fs-backed /vendor)
+        // still re-walks every partition it touches, so letting an earlier
+        // partition land in `partition_map` would get it both
+        // overlay-mounted in Pass 1 and magic-mounted in Pass 2.
+        let mut candidate_parts: Vec<&str> = Vec::new();
+        let mut forced_to_magic = is_magic;
         for &part in &all_partitions {
-            let part_dir = content_path.join(part);
-            if part_dir.is_dir() {
-                partition_map
-                    .entry(part.to_string())
-                    .or_default()
-                    .push(content_path.clone());
-
-                if is_magic {
-                    magic_force_map.insert(part.to_string(), true);
-                    tracing::info!("Partition /{} forced to Magic Mount by module '{}'", part, module_id);
+            if content_path.join(part).is_dir() {
+                if fs_forced_partitions.contains(part) {
+                    forced_to_magic = true;
+                }
+                candidate_parts.push(part);
+            }
+        }
+
+        if forced_to_magic {
+            magic_modules.insert(content_path.clone());
+            for &part in &candidate_parts {
+                magic_force_map.insert(part.to_string(), true);
+                tracing::info!("Module '{}' routed whole to Magic Mount (partition /{})", module_id, part);
+            }
+            continue;
+        }
+
+        for &part in &candidate_parts {
+            partition_map
+                .entry(part.to_string())
+                .or_default()
+                .push(content_path.clone());
+
+            if !module_whiteouts.is_empty() {
+                let for_part = whiteout::for_partition(&module_whiteouts, part);
+                if !for_part.is_empty() {
+                    partition_whiteouts.entry(part.to_string()).or_default().extend(for_part);
                 }
             }
         }
@@ -57,8 +107,6 @@ pub fn run(active_modules: HashMap<String, PathBuf>, config: &Config) -> Result<
     } else {
         utils::select_temp_dir()?
     };
-    
-    let mut magic_modules: HashSet<PathBuf> = HashSet::new();
 
     // Pass 1: OverlayFS
     for (part, modules) in &partition_map {
@@ -75,9 +123,21 @@ pub fn run(active_modules: HashMap<String, PathBuf>, config: &Config) -> Result<
                 .map(|m| m.join(part).display().to_string())
                 .collect();
 
-            tracing::info!("Mounting {} [OVERLAY] ({} layers)", target_path, overlay_paths.len());
-            
-            if let Err(e) = overlay_mount::mount_overlay(&target_path, &overlay_paths, None, None) {
+            let whiteouts = partition_whiteouts.get(part).map(Vec::as_slice).unwrap_or(&[]);
+            tracing::info!(
+                "Mounting {} [OVERLAY] ({} layers, {} whiteouts)",
+                target_path, overlay_paths.len(), whiteouts.len()
+            );
+
+            if let Err(e) = overlay_mount::mount_overlay(
+                &target_path,
+                &overlay_paths,
+                None,
+                None,
+                whiteouts,
+                config.propagation,
+                false,
+            ) {
                 tracing::error!(
                     "OverlayFS mount failed for {}: {:#}, falling back to Magic Mount",
                     target_path, e
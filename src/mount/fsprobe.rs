@@ -0,0 +1,69 @@
+// src/mount/fsprobe.rs
+// Proactively probes the filesystem backing a mount point so the planner can
+// steer away from stacking a second OverlayFS on top of a backing store
+// that's known-problematic for it, instead of only finding out reactively
+// once `mount_overlay` has already failed.
+use std::path::Path;
+
+use rustix::fs::statfs;
+
+// statfs(2) f_type magic numbers we care about (see linux/magic.h).
+const OVERLAYFS_SUPER_MAGIC: u64 = 0x794c_7630;
+const F2FS_SUPER_MAGIC: u64 = 0xf2f5_2010;
+const NFS_SUPER_MAGIC: u64 = 0x6969;
+const EXT4_SUPER_MAGIC: u64 = 0xef53;
+const TMPFS_MAGIC: u64 = 0x0102_1994;
+
+/// Backing filesystem kind detected for a mount point, as reported by
+/// `statfs`'s `f_type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsKind {
+    Ext4,
+    Tmpfs,
+    F2fs,
+    Overlay,
+    Nfs,
+    Other(u64),
+}
+
+impl FsKind {
+    fn from_magic(magic: u64) -> FsKind {
+        match magic {
+            EXT4_SUPER_MAGIC => FsKind::Ext4,
+            TMPFS_MAGIC => FsKind::Tmpfs,
+            F2FS_SUPER_MAGIC => FsKind::F2fs,
+            OVERLAYFS_SUPER_MAGIC => FsKind::Overlay,
+            NFS_SUPER_MAGIC => FsKind::Nfs,
+            other => FsKind::Other(other),
+        }
+    }
+
+    /// True when stacking a second OverlayFS on top of this backing store is
+    /// known-problematic: it's already an overlay (nested overlays are
+    /// fragile across kernel versions), it's a network filesystem whose
+    /// locking/consistency model overlayfs doesn't get along with, or it's
+    /// f2fs, whose atomic/fsync-heavy write path has known corruption
+    /// reports under a stacked overlay on some kernel configurations.
+    pub fn overlay_unsafe(self) -> bool {
+        matches!(self, FsKind::Overlay | FsKind::Nfs | FsKind::F2fs)
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            FsKind::Ext4 => "ext4".to_string(),
+            FsKind::Tmpfs => "tmpfs".to_string(),
+            FsKind::F2fs => "f2fs".to_string(),
+            FsKind::Overlay => "overlay".to_string(),
+            FsKind::Nfs => "nfs".to_string(),
+            FsKind::Other(magic) => format!("unknown(0x{magic:x})"),
+        }
+    }
+}
+
+/// Detects the filesystem backing `path` via `statfs`. Returns `None` if the
+/// path doesn't exist or `statfs` fails (e.g. not yet mounted), leaving the
+/// caller to fall back to whatever default it already had.
+pub fn detect(path: &Path) -> Option<FsKind> {
+    let stat = statfs(path).ok()?;
+    Some(FsKind::from_magic(stat.f_type as u64))
+}
@@ -0,0 +1,121 @@
+// src/mount/loopdev.rs
+// Loop-backed ext4 image storage backend: keeps all prepared module data in
+// one opaque file instead of a plain directory tree under `target_base`,
+// which is what nuke.rs's ext4-trace scrubbing actually needs to be coherent.
+use std::fs::{File, OpenOptions};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use rustix::mount::{mount, MountFlags};
+
+const LOOP_CONTROL_PATH: &str = "/dev/loop-control";
+
+// Loop device ioctls (linux/loop.h); not exposed by rustix, so we go through
+// libc directly like the rest of the LKM-adjacent code in this crate.
+const LOOP_SET_FD: u64 = 0x4C00;
+const LOOP_CLR_FD: u64 = 0x4C01;
+const LOOP_CTL_GET_FREE: u64 = 0x4C82;
+
+/// Creates the sparse backing file for the module storage image if it
+/// doesn't already exist, sized `size_bytes`. Returns whether the file was
+/// freshly created (and therefore still needs an ext4 filesystem written).
+fn ensure_backing_file(path: &Path, size_bytes: u64) -> Result<bool> {
+    if path.exists() {
+        return Ok(false);
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("creating parent dir for {}", path.display()))?;
+    }
+    let file = File::create(path).with_context(|| format!("creating image {}", path.display()))?;
+    file.set_len(size_bytes)
+        .with_context(|| format!("sizing image {} to {} bytes", path.display(), size_bytes))?;
+    Ok(true)
+}
+
+/// Attaches `backing_file` to the first free loop device and returns its path
+/// (e.g. `/dev/loop7`).
+pub fn attach(backing_file: &Path) -> Result<PathBuf> {
+    let ctl = File::open(LOOP_CONTROL_PATH).with_context(|| format!("opening {LOOP_CONTROL_PATH}"))?;
+    let free_index = unsafe { libc::ioctl(ctl.as_raw_fd(), LOOP_CTL_GET_FREE as _) };
+    if free_index < 0 {
+        bail!("LOOP_CTL_GET_FREE failed: {}", std::io::Error::last_os_error());
+    }
+
+    let loop_path = PathBuf::from(format!("/dev/loop{free_index}"));
+    let loop_dev = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&loop_path)
+        .with_context(|| format!("opening {}", loop_path.display()))?;
+    let backing = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(backing_file)
+        .with_context(|| format!("opening backing file {}", backing_file.display()))?;
+
+    let rc = unsafe { libc::ioctl(loop_dev.as_raw_fd(), LOOP_SET_FD as _, backing.as_raw_fd()) };
+    if rc < 0 {
+        bail!("LOOP_SET_FD failed for {}: {}", loop_path.display(), std::io::Error::last_os_error());
+    }
+
+    log::info!("Attached {} to {}", backing_file.display(), loop_path.display());
+    Ok(loop_path)
+}
+
+/// Detaches a loop device previously returned by [`attach`].
+pub fn detach(loop_dev: &Path) -> Result<()> {
+    let dev = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(loop_dev)
+        .with_context(|| format!("opening {}", loop_dev.display()))?;
+    let rc = unsafe { libc::ioctl(dev.as_raw_fd(), LOOP_CLR_FD as _, 0) };
+    if rc < 0 {
+        bail!("LOOP_CLR_FD failed for {}: {}", loop_dev.display(), std::io::Error::last_os_error());
+    }
+    log::info!("Detached {}", loop_dev.display());
+    Ok(())
+}
+
+/// Ensures the ext4 storage image at `image_path` exists, attaches it via a
+/// loop device, and mounts it at `target_base`. Returns the loop device path
+/// so the caller can hand it to [`teardown`] later.
+pub fn mount_ext4_image(image_path: &Path, target_base: &Path, size_bytes: u64) -> Result<PathBuf> {
+    let fresh = ensure_backing_file(image_path, size_bytes)?;
+    let loop_dev = attach(image_path)?;
+
+    if fresh {
+        log::info!("Formatting {} as ext4", loop_dev.display());
+        let status = Command::new("mkfs.ext4")
+            .arg("-q")
+            .arg(&loop_dev)
+            .status()
+            .with_context(|| "spawning mkfs.ext4")?;
+        if !status.success() {
+            let _ = detach(&loop_dev);
+            bail!("mkfs.ext4 failed on {}", loop_dev.display());
+        }
+    }
+
+    std::fs::create_dir_all(target_base)
+        .with_context(|| format!("creating mount point {}", target_base.display()))?;
+
+    if let Err(e) = mount(&loop_dev, target_base, "ext4", MountFlags::empty(), "") {
+        let _ = detach(&loop_dev);
+        return Err(e).with_context(|| format!("mounting {} at {}", loop_dev.display(), target_base.display()));
+    }
+
+    log::info!("Mounted ext4 image {} at {}", image_path.display(), target_base.display());
+    Ok(loop_dev)
+}
+
+/// Unmounts `target_base` and detaches its backing loop device.
+pub fn teardown(target_base: &Path, loop_dev: &Path) -> Result<()> {
+    if let Err(e) = rustix::mount::unmount(target_base, rustix::mount::UnmountFlags::empty()) {
+        log::warn!("Failed to unmount {}: {}", target_base.display(), e);
+    }
+    detach(loop_dev)
+}
@@ -3,15 +3,76 @@
 
 use anyhow::{Context, Result};
 use log::{info, warn};
+use std::fs;
 use std::path::{Path, PathBuf};
 
 use procfs::process::Process;
 use rustix::{fd::AsFd, fs::CWD, mount::*};
 
-use crate::defs::KSU_OVERLAY_SOURCE;
+use crate::core::whiteout::{self, Whiteout};
+use crate::defs::{self, KSU_OVERLAY_SOURCE};
 use crate::utils::send_unmountable;
 
+/// Mount propagation mode applied to a target subtree before we stack an
+/// overlay over it, so our mounts are isolated from (or deliberately linked
+/// to) the rest of the mount namespace tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Propagation {
+    /// Recursively private: nothing we do here leaks into other namespaces
+    /// (e.g. zygote-forked app namespaces), and host-side namespace churn
+    /// can't tear our overlay down either. Safe default.
+    #[default]
+    Private,
+    /// Mount events flow one-way from the host namespace into ours.
+    Slave,
+    /// Mount events propagate both ways.
+    Shared,
+    /// No propagation at all, in either direction.
+    Unbindable,
+}
+
+impl std::str::FromStr for Propagation {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "private" => Ok(Propagation::Private),
+            "slave" => Ok(Propagation::Slave),
+            "shared" => Ok(Propagation::Shared),
+            "unbindable" => Ok(Propagation::Unbindable),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Propagation {
+    fn flags(self) -> MountPropagationFlags {
+        let base = match self {
+            Propagation::Private => MountPropagationFlags::PRIVATE,
+            Propagation::Slave => MountPropagationFlags::SLAVE,
+            Propagation::Shared => MountPropagationFlags::SHARED,
+            Propagation::Unbindable => MountPropagationFlags::UNBINDABLE,
+        };
+        base | MountPropagationFlags::REC
+    }
+}
+
+/// Recursively remounts `target` with the chosen propagation mode. Must run
+/// before the overlay goes up, so the mode applies to every mount already
+/// nested under `target` as well as the overlay itself.
+fn set_propagation(target: impl AsRef<Path>, propagation: Propagation) -> Result<()> {
+    mount_change(target.as_ref(), propagation.flags())
+        .with_context(|| format!("setting {:?} propagation on {:?}", propagation, target.as_ref()))
+}
+
 /// Low-level function to mount overlayfs using modern fsopen API or fallback to mount()
+///
+/// Does not touch mount propagation: by the time this runs for a child mount
+/// point (see `mount_overlay_child`), that path has already been shadowed by
+/// the root overlay and is no longer a mountpoint itself, so calling
+/// `set_propagation` on it here would fail with EINVAL. Propagation is set
+/// once, on `target_root`, before the root overlay goes up — see
+/// `mount_overlay`.
 pub fn mount_overlayfs(
     lower_dirs: &[String],
     lowest: &str,
@@ -119,6 +180,7 @@ fn mount_overlay_child(
     relative: &str,
     module_roots: &[String],
     stock_root: &str,
+    whiteouts: &[Whiteout],
     disable_umount: bool,
 ) -> Result<()> {
     // Check if any module modifies this child path
@@ -160,8 +222,28 @@ fn mount_overlay_child(
         return Ok(());
     }
 
+    // A module's REMOVE entry or `.replace` marker targeting a path under
+    // this child mount (e.g. `/system/vendor/app/Bloat.apk`) is only ever
+    // realized in whichever upperdir the mount that actually covers that
+    // path looks at — the root overlay's upperdir is invisible to a nested
+    // child overlay. Provision this child its own upperdir/workdir, same as
+    // `mount_overlay` does for the root, whenever it has deletions of its own.
+    // A child that no module adds files under is bind-mounted back unchanged
+    // above, so deletions under it alone are not realized.
+    let (upperdir, workdir) = if !whiteouts.is_empty() {
+        let base = Path::new(defs::SYSTEM_RW_DIR).join(mount_point.trim_start_matches('/'));
+        let (up, wd) = (base.join("upper"), base.join("work"));
+        fs::create_dir_all(&up).with_context(|| format!("creating upperdir {}", up.display()))?;
+        fs::create_dir_all(&wd).with_context(|| format!("creating workdir {}", wd.display()))?;
+        whiteout::apply_whiteouts(&up, whiteouts)
+            .with_context(|| format!("realizing whiteouts for child {mount_point}"))?;
+        (Some(up), Some(wd))
+    } else {
+        (None, None)
+    };
+
     // Merge modules and original stock child
-    if let Err(e) = mount_overlayfs(&lower_dirs, stock_root, None, None, mount_point, disable_umount) {
+    if let Err(e) = mount_overlayfs(&lower_dirs, stock_root, upperdir, workdir, mount_point, disable_umount) {
         warn!("failed to overlay child {mount_point}: {e:#}, fallback to bind mount");
         bind_mount(stock_root, mount_point, disable_umount)?;
     }
@@ -174,14 +256,37 @@ pub fn mount_overlay(
     module_roots: &[String], // List of module paths containing "system"
     workdir: Option<PathBuf>,
     upperdir: Option<PathBuf>,
+    whiteouts: &[Whiteout],
+    propagation: Propagation,
     disable_umount: bool,
 ) -> Result<()> {
-    info!("Starting robust overlay mount for {target_root}");
-    
+    info!("Starting robust overlay mount for {target_root} (propagation: {propagation:?})");
+
+    // If any module declares deletions for this target, we need a real
+    // upper/workdir so we can actually realize them (overlay whiteouts only
+    // exist in the upperdir, never in a lowerdir). Provision one under our
+    // own storage when the caller didn't already supply one.
+    let (upperdir, workdir) = if !whiteouts.is_empty() {
+        let (up, wd) = match (upperdir, workdir) {
+            (Some(up), Some(wd)) => (up, wd),
+            _ => {
+                let base = Path::new(defs::SYSTEM_RW_DIR).join(target_root.trim_start_matches('/'));
+                (base.join("upper"), base.join("work"))
+            }
+        };
+        fs::create_dir_all(&up).with_context(|| format!("creating upperdir {}", up.display()))?;
+        fs::create_dir_all(&wd).with_context(|| format!("creating workdir {}", wd.display()))?;
+        whiteout::apply_whiteouts(&up, whiteouts)
+            .with_context(|| format!("realizing whiteouts for {target_root}"))?;
+        (Some(up), Some(wd))
+    } else {
+        (upperdir, workdir)
+    };
+
     // 1. Change to target directory to ensure relative paths work and we hold a ref
     std::env::set_current_dir(target_root)
         .with_context(|| format!("failed to chdir to {target_root}"))?;
-    
+
     let stock_root = "."; // Represents the original content of target_root
 
     // 2. Scan for existing child mounts under this target
@@ -203,7 +308,11 @@ pub fn mount_overlay(
     mount_seq.sort();
     mount_seq.dedup();
 
-    // 3. Mount the Root Overlay
+    // 3. Set propagation on the root, then mount the Root Overlay.
+    // Must happen before the overlay goes up: once mounted, target_root's
+    // old mountpoint is shadowed by the new overlay and no longer exists as
+    // a distinct mount for `mount_change` to retarget.
+    set_propagation(target_root, propagation)?;
     mount_overlayfs(module_roots, target_root, upperdir, workdir, target_root, disable_umount)
         .with_context(|| format!("mount overlayfs for root {target_root} failed"))?;
 
@@ -219,7 +328,12 @@ pub fn mount_overlay(
             continue;
         }
 
-        if let Err(e) = mount_overlay_child(&mount_point, &relative, module_roots, &stock_root_relative, disable_umount) {
+        // `whiteouts` is already relative to `target_root`; strip this
+        // child's own relative prefix so only the deletions that actually
+        // fall under it are realized in its upperdir.
+        let child_whiteouts = whiteout::for_partition(whiteouts, relative.trim_start_matches('/'));
+
+        if let Err(e) = mount_overlay_child(&mount_point, &relative, module_roots, &stock_root_relative, &child_whiteouts, disable_umount) {
             warn!("failed to restore child mount {mount_point}: {e:#}");
             // Don't bail, try next child
         }
@@ -0,0 +1,58 @@
+// src/mount/teardown.rs
+// Symmetric counterpart to overlay::mount_overlay: lets a module be disabled
+// live, without a reboot, by tearing its mounts back down.
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use procfs::process::Process;
+use rustix::mount::{unmount, UnmountFlags};
+
+use crate::defs::KSU_OVERLAY_SOURCE;
+
+/// Tears down every mount we manage: anything sourced from
+/// [`KSU_OVERLAY_SOURCE`] (our overlays) or rooted under `managed_roots`
+/// (e.g. bind mounts into module storage). Idempotent and safe to call
+/// repeatedly — a mount point that's already gone is simply skipped.
+pub fn teardown(managed_roots: &[&Path]) -> Result<()> {
+    let mounts = Process::myself()?
+        .mountinfo()
+        .with_context(|| "get mountinfo")?;
+
+    let mut targets: Vec<(usize, String)> = mounts
+        .0
+        .iter()
+        .filter(|m| {
+            m.mount_source.as_deref() == Some(KSU_OVERLAY_SOURCE)
+                || managed_roots.iter().any(|root| m.mount_point.starts_with(root))
+        })
+        .map(|m| {
+            let mount_point = m.mount_point.to_string_lossy().to_string();
+            let depth = mount_point.matches('/').count();
+            (depth, mount_point)
+        })
+        .collect();
+
+    // Children unmount before parents, or the parent unmount would orphan
+    // them (or simply fail as "device busy").
+    targets.sort_by(|a, b| b.0.cmp(&a.0));
+    targets.dedup_by(|a, b| a.1 == b.1);
+
+    for (_, mount_point) in targets {
+        unmount_one(&mount_point);
+    }
+
+    Ok(())
+}
+
+fn unmount_one(mount_point: &str) {
+    let path = Path::new(mount_point);
+    if let Err(e) = unmount(path, UnmountFlags::empty()) {
+        warn!("unmount {mount_point} failed ({e}), retrying with MNT_DETACH");
+        if let Err(e) = unmount(path, UnmountFlags::DETACH) {
+            warn!("lazy unmount of {mount_point} also failed: {e}");
+            return;
+        }
+    }
+    info!("Unmounted {mount_point}");
+}